@@ -1,79 +1,282 @@
 use clipcat::ClipboardData;
-use serde::Serialize;
 use snafu::ResultExt;
-use std::io::{self, Seek};
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
 
 use crate::history::{error, HistoryDriver, HistoryError};
 
+/// Above this dead-record ratio, `shrink_to` compacts the log instead of
+/// just dropping the oldest entries from the in-memory index.
+const COMPACTION_THRESHOLD: f64 = 0.5;
+
+/// An append-only, length-prefixed bincode log, replacing the previous
+/// full-file read-modify-rewrite design: `put` no longer has to
+/// deserialize and re-serialize the entire history on every single
+/// clipboard event, and a crash mid-write can at worst truncate the last
+/// unfinished record, never the whole file.
+///
+/// Layout on disk is a sequence of records, each `[u64 length][bincode
+/// ClipboardData]`. `shrink_to` drops the oldest entries logically (they
+/// stay on disk as dead bytes) and only pays for a physical compaction
+/// once the dead-record ratio crosses [`COMPACTION_THRESHOLD`].
 pub struct SimpleDBDriver {
     path: PathBuf,
+    /// Byte offset of every *live* record currently in the file, oldest
+    /// first.
+    offsets: VecDeque<u64>,
+    /// Records appended since the last compaction that are no longer live
+    /// (dropped by `shrink_to`). Tracked so we know when compaction is
+    /// worth the I/O.
+    dead_count: usize,
 }
+
 impl SimpleDBDriver {
-    pub fn new(path: impl AsRef<Path>) -> Self {
-        Self { path: path.as_ref().to_path_buf() }
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, HistoryError> {
+        let path = path.as_ref().to_path_buf();
+        let offsets = read_offsets(&path)?;
+
+        // Cut off a truncated trailing record skipped by `read_offsets`, so
+        // the next append starts right after the last complete record
+        // instead of after the leftover bytes of a crashed write.
+        let valid_len = match offsets.back() {
+            Some(&last) => {
+                let data = read_record_at(&path, last)?;
+                last + 8 + bincode::serialized_size(&data).context(error::Serde)?
+            }
+            None => 0,
+        };
+        if let Ok(file) = std::fs::OpenOptions::new().write(true).open(&path) {
+            if file.metadata().context(error::Io)?.len() > valid_len {
+                file.set_len(valid_len).context(error::Io)?;
+            }
+        }
+
+        Ok(Self { path, offsets, dead_count: 0 })
     }
 
-    fn write(&self, data: Vec<ClipboardData>) -> Result<(), HistoryError> {
-        let mut file = std::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(false)
-            .open(&self.path)
-            .context(error::Io)?;
-        file.set_len(0).context(error::Io)?;
-        // file.seek(io::SeekFrom::Start(0));
-        // println!("Writing {:#?}", data);
-        bincode::serialize_into(&mut file, &FileContents { data }).context(error::Serde)?;
+    fn open_append(&self) -> Result<std::fs::File, HistoryError> {
+        std::fs::OpenOptions::new().create(true).append(true).open(&self.path).context(error::Io)
+    }
+
+    /// Appends one record, fsyncing before returning so a crash right
+    /// after `put` can't lose an acknowledged write.
+    fn append(&mut self, data: &ClipboardData) -> Result<(), HistoryError> {
+        let mut file = self.open_append()?;
+        let offset = file.metadata().context(error::Io)?.len();
+        write_record(&mut file, data)?;
+        file.sync_all().context(error::Io)?;
+        self.offsets.push_back(offset);
+        Ok(())
+    }
+
+    fn dead_ratio(&self) -> f64 {
+        let total = self.offsets.len() + self.dead_count;
+        if total == 0 {
+            0.0
+        } else {
+            self.dead_count as f64 / total as f64
+        }
+    }
+
+    /// Rewrites the file with only the currently-live records, via a
+    /// temp-file-then-rename so a crash mid-compaction leaves the original
+    /// file untouched.
+    fn compact(&mut self) -> Result<(), HistoryError> {
+        let live = self.load()?;
+
+        let tmp_path = self.path.with_extension("tmp");
+        let mut offsets = VecDeque::with_capacity(live.len());
+        {
+            let mut tmp_file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&tmp_path)
+                .context(error::Io)?;
+            let mut offset = 0u64;
+            for data in &live {
+                offsets.push_back(offset);
+                write_record(&mut tmp_file, data)?;
+                offset = tmp_file.metadata().context(error::Io)?.len();
+            }
+            tmp_file.sync_all().context(error::Io)?;
+        }
+
+        // Only adopt the new layout once the rename has actually landed —
+        // if it fails (EXDEV, disk full, permissions), `self.path` still
+        // holds the untouched original file, so our in-memory index must
+        // keep describing that file, not the abandoned temp file.
+        std::fs::rename(&tmp_path, &self.path).context(error::Io)?;
+        self.offsets = offsets;
+        self.dead_count = 0;
         Ok(())
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
-struct FileContents {
-    data: Vec<ClipboardData>,
+/// Writes one `[u64 length][bincode data]` record.
+fn write_record(file: &mut std::fs::File, data: &ClipboardData) -> Result<(), HistoryError> {
+    let bytes = bincode::serialize(data).context(error::Serde)?;
+    file.write_all(&(bytes.len() as u64).to_le_bytes()).context(error::Io)?;
+    file.write_all(&bytes).context(error::Io)?;
+    Ok(())
+}
+
+/// Replays the log, returning the offset of every complete record found.
+/// A truncated trailing record (the tell-tale sign of a crash mid-`put`)
+/// is silently dropped rather than treated as corruption.
+fn read_offsets(path: &Path) -> Result<VecDeque<u64>, HistoryError> {
+    let mut offsets = VecDeque::new();
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(offsets),
+        Err(err) => return Err(err).context(error::Io),
+    };
+
+    let mut offset = 0u64;
+    loop {
+        let mut len_bytes = [0u8; 8];
+        match file.read_exact(&mut len_bytes) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err).context(error::Io),
+        }
+        let len = u64::from_le_bytes(len_bytes);
+
+        let mut payload = vec![0u8; len as usize];
+        match file.read_exact(&mut payload) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err).context(error::Io),
+        }
+
+        offsets.push_back(offset);
+        offset += 8 + len;
+    }
+
+    Ok(offsets)
+}
+
+/// Reads the record at `offset`.
+fn read_record_at(path: &Path, offset: u64) -> Result<ClipboardData, HistoryError> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = std::fs::File::open(path).context(error::Io)?;
+    file.seek(SeekFrom::Start(offset)).context(error::Io)?;
+    let mut reader = BufReader::new(file);
+
+    let mut len_bytes = [0u8; 8];
+    reader.read_exact(&mut len_bytes).context(error::Io)?;
+    let len = u64::from_le_bytes(len_bytes);
+
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).context(error::Io)?;
+    bincode::deserialize(&payload).context(error::Serde)
 }
 
 impl HistoryDriver for SimpleDBDriver {
     fn load(&self) -> Result<Vec<ClipboardData>, HistoryError> {
-        println!("CAlled load");
-        let data = match std::fs::File::open(&self.path) {
-            Ok(mut file) => bincode::deserialize_from(&mut file).context(error::Serde)?,
-            Err(err) => match err.kind() {
-                io::ErrorKind::NotFound => Vec::new(),
-                _ => return Err(err).context(error::Io),
-            },
-        };
-        Ok(data)
+        self.offsets.iter().map(|&offset| read_record_at(&self.path, offset)).collect()
     }
+
     fn save(&mut self, data: &[ClipboardData]) -> Result<(), HistoryError> {
-        println!("CAlled save {}", data.len());
-        let mut saved = self.load()?;
         for data in data {
-            saved.push(data.clone());
+            self.append(data)?;
         }
-        self.write(saved)
+        Ok(())
     }
+
     fn clear(&mut self) -> Result<(), HistoryError> {
-        println!("Called clear");
-        self.write(Vec::new())
+        std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)
+            .context(error::Io)?;
+        self.offsets.clear();
+        self.dead_count = 0;
+        Ok(())
     }
+
     fn put(&mut self, data: &ClipboardData) -> Result<(), HistoryError> {
-        println!("Called put {:#?}", data);
-        let mut saved = self.load()?;
-        saved.push(data.clone());
-        self.write(saved)
+        self.append(data)
     }
+
     fn shrink_to(&mut self, min_capacity: usize) -> Result<(), HistoryError> {
-        println!("Called shrink to {}", min_capacity);
-        let mut saved = self.load()?;
+        let to_drop = self.offsets.len().saturating_sub(min_capacity);
+        for _ in 0..to_drop {
+            self.offsets.pop_front();
+            self.dead_count += 1;
+        }
+
+        if self.dead_ratio() > COMPACTION_THRESHOLD {
+            self.compact()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("clipcat-simpledb-test-{}-{}.db", std::process::id(), name))
+    }
+
+    fn sample(text: &str) -> ClipboardData {
+        ClipboardData::new_clipboard(text)
+    }
+
+    #[test]
+    fn load_drops_a_truncated_trailing_record() {
+        let path = temp_db_path("truncated");
+        let _ = std::fs::remove_file(&path);
+
+        let mut driver = SimpleDBDriver::new(&path).unwrap();
+        driver.put(&sample("first")).unwrap();
+        driver.put(&sample("second")).unwrap();
 
-        let to_shrink = saved.len().saturating_sub(min_capacity);
-        println!("Shrinking with {}", to_shrink);
-        for _ in 0..to_shrink {
-            saved.remove(0);
+        // Simulate a crash mid-write: append a length prefix that claims
+        // more payload bytes than actually follow it.
+        {
+            let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(&100u64.to_le_bytes()).unwrap();
+            file.write_all(b"not enough bytes").unwrap();
         }
 
-        self.write(saved)
+        let reopened = SimpleDBDriver::new(&path).unwrap();
+        let loaded = reopened.load().unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn shrink_to_compacts_once_past_the_dead_ratio_threshold() {
+        let path = temp_db_path("compaction");
+        let _ = std::fs::remove_file(&path);
+
+        let mut driver = SimpleDBDriver::new(&path).unwrap();
+        for i in 0..4 {
+            driver.put(&sample(&format!("entry-{}", i))).unwrap();
+        }
+
+        // Dropping 1 of 4 (25% dead) stays under COMPACTION_THRESHOLD: no
+        // compaction, the file still holds all 4 records physically.
+        driver.shrink_to(3).unwrap();
+        assert_eq!(driver.dead_count, 1);
+        let size_before_compaction = std::fs::metadata(&path).unwrap().len();
+
+        // Dropping down to 1 of the remaining 3 (now 3 of 4 ever written
+        // are dead) crosses the threshold and triggers a compaction.
+        driver.shrink_to(1).unwrap();
+        assert_eq!(driver.dead_count, 0);
+        assert_eq!(driver.load().unwrap().len(), 1);
+        let size_after_compaction = std::fs::metadata(&path).unwrap().len();
+        assert!(size_after_compaction < size_before_compaction);
+
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,11 @@
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility = "pub")]
+pub enum CliprdrError {
+    #[snafu(display("CLIPRDR virtual channel is not available on this session"))]
+    ChannelNotAvailable,
+
+    #[snafu(display("Remote peer requested unsupported clipboard format id: {}", format_id))]
+    UnsupportedFormat { format_id: u32 },
+}
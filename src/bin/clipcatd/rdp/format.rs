@@ -0,0 +1,80 @@
+/// Windows clipboard format IDs clipcat knows how to translate to/from a
+/// stored MIME type, per `[MS-RDPECLIP]`.
+const CF_TEXT: u32 = 1;
+const CF_UNICODETEXT: u32 = 13;
+
+/// Maps a clipcat MIME type to the CLIPRDR format ID(s) we advertise for
+/// it in a `FormatList` PDU. Unsupported MIME types are simply not
+/// advertised; the remote peer only ever asks for formats we listed.
+///
+/// We only ever advertise `CF_UNICODETEXT`: it's a strict superset of what
+/// `CF_TEXT` (legacy 8-bit ANSI) can carry, so there's no reason to offer
+/// the lossier format ourselves. `CF_TEXT` is still accepted on the
+/// decoding side below, since we don't control what a remote peer sends.
+pub fn mime_to_format_id(mime: &str) -> Option<u32> {
+    match mime {
+        "text/plain" | "text/plain;charset=utf-8" => Some(CF_UNICODETEXT),
+        _ => None,
+    }
+}
+
+/// The inverse of [`mime_to_format_id`], used to validate the format ID of
+/// a `FormatDataRequest` before serving it. Both
+/// `CF_UNICODETEXT` and `CF_TEXT` map to the same `"text/plain"` MIME
+/// type — callers that need to know which wire encoding applies should
+/// decode via [`decode_text`]/[`encode_text`] rather than assuming
+/// UTF-16LE from the MIME type alone.
+pub fn format_id_to_mime(format_id: u32) -> Option<&'static str> {
+    match format_id {
+        CF_UNICODETEXT | CF_TEXT => Some("text/plain"),
+        _ => None,
+    }
+}
+
+/// Decodes clipboard text off the wire according to its actual format ID:
+/// `CF_UNICODETEXT` is UTF-16LE with a trailing NUL, `CF_TEXT` is 8-bit
+/// ANSI (treated here as Latin-1, the common case) with a trailing NUL.
+/// Decoding `CF_TEXT` as UTF-16LE would cut the string in half and
+/// garble every character, so the format ID has to reach this far.
+pub fn decode_text(format_id: u32, bytes: &[u8]) -> Option<String> {
+    match format_id {
+        CF_UNICODETEXT => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .take_while(|&unit| unit != 0)
+                .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        CF_TEXT => {
+            let text: String = bytes
+                .iter()
+                .take_while(|&&byte| byte != 0)
+                .map(|&byte| byte as char)
+                .collect();
+            Some(text)
+        }
+        _ => None,
+    }
+}
+
+/// Encodes `text` for the wire as `format_id` expects it. We only ever
+/// advertise `CF_UNICODETEXT` (see [`mime_to_format_id`]), so this is what
+/// `FormatDataRequest` handling always calls with in practice; `CF_TEXT`
+/// is supported here too for symmetry with [`decode_text`].
+pub fn encode_text(format_id: u32, text: &str) -> Option<Vec<u8>> {
+    match format_id {
+        CF_UNICODETEXT => {
+            let mut units: Vec<u16> = text.encode_utf16().collect();
+            units.push(0);
+            Some(units.iter().flat_map(|unit| unit.to_le_bytes()).collect())
+        }
+        CF_TEXT => {
+            let mut bytes: Vec<u8> =
+                text.chars().map(|ch| if ch as u32 <= 0xFF { ch as u8 } else { b'?' }).collect();
+            bytes.push(0);
+            Some(bytes)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,140 @@
+mod error;
+mod format;
+
+use std::sync::{Arc, Mutex};
+
+use clipcat::{content::ClipboardContent, ClipboardEvent};
+use tokio::sync::broadcast;
+
+pub use self::error::CliprdrError;
+
+/// Bridges clipcat's local `broadcast::Sender<ClipboardEvent>` to a remote
+/// peer over the RDP clipboard virtual channel (CLIPRDR), modeled on the
+/// qemu-rdp cliprdr backend: local copies are announced to the remote
+/// side, and the remote side's copies are injected back in as synthetic
+/// `ClipboardEvent`s so both ends of the session share one clipboard.
+///
+/// Only the clipcat <-> CLIPRDR translation lives here; the actual virtual
+/// channel I/O (PDU framing, the RDP session itself) is provided by the
+/// caller, since it depends on which RDP server/client library clipcatd is
+/// embedded in.
+pub struct CliprdrBackend {
+    /// What we'd currently announce in a `FormatList` PDU: the most recent
+    /// local clipboard event, kept around so `FormatDataRequest` can be
+    /// served without re-reading the clipboard or history store.
+    last_local: Arc<Mutex<Option<ClipboardContent>>>,
+    /// What the remote side most recently sent us, used to dedup the next
+    /// inbound `FormatDataResponse` the same way `ClipboardMonitor`
+    /// dedups local selection changes.
+    last_remote: Arc<Mutex<Option<ClipboardContent>>>,
+    sender: broadcast::Sender<ClipboardEvent>,
+    filter_min_size: usize,
+}
+
+impl CliprdrBackend {
+    /// `receiver` feeds the local -> remote announce loop; `sender` is the
+    /// same bus the monitor/history store use, so remote -> local copies
+    /// land in history exactly like a local clipboard change would.
+    pub fn new(
+        sender: broadcast::Sender<ClipboardEvent>,
+        filter_min_size: usize,
+    ) -> (Self, broadcast::Receiver<ClipboardEvent>) {
+        let receiver = sender.subscribe();
+        let backend = Self {
+            last_local: Arc::new(Mutex::new(None)),
+            last_remote: Arc::new(Mutex::new(None)),
+            sender,
+            filter_min_size,
+        };
+        (backend, receiver)
+    }
+
+    /// Runs the local -> remote announce loop: whenever a new
+    /// `ClipboardEvent` is broadcast locally, remember it and hand its
+    /// available format IDs back to the caller to put in a `FormatList`
+    /// PDU.
+    pub async fn run_local_announce_loop(
+        &self,
+        mut receiver: broadcast::Receiver<ClipboardEvent>,
+        mut on_format_list: impl FnMut(Vec<u32>),
+    ) {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("CLIPRDR announce loop lagged, skipped {} events", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+
+            let content = event.content().clone();
+            *self.last_local.lock().expect("last_local mutex poisoned") = Some(content.clone());
+
+            let format_ids = available_format_ids(&content);
+            if !format_ids.is_empty() {
+                on_format_list(format_ids);
+            }
+        }
+    }
+
+    /// Serves a remote `FormatDataRequest` for `format_id` out of the most
+    /// recently announced local clipboard content.
+    pub fn on_format_data_request(&self, format_id: u32) -> Result<Vec<u8>, CliprdrError> {
+        // Validates `format_id` against the MIME map first, so an id with no
+        // MIME mapping is rejected before the local content is looked at.
+        format::format_id_to_mime(format_id).ok_or(CliprdrError::UnsupportedFormat { format_id })?;
+
+        let last_local = self.last_local.lock().expect("last_local mutex poisoned");
+        let content = last_local.as_ref().ok_or(CliprdrError::UnsupportedFormat { format_id })?;
+
+        let text = match content {
+            ClipboardContent::Text(text) => Some(text.as_str()),
+            _ => content.text_fallback().map(|bytes| std::str::from_utf8(bytes).unwrap_or_default()),
+        };
+        let text = text.ok_or(CliprdrError::UnsupportedFormat { format_id })?;
+
+        format::encode_text(format_id, text).ok_or(CliprdrError::UnsupportedFormat { format_id })
+    }
+
+    /// Injects a remote `FormatDataResponse` back into clipcat as a
+    /// synthetic `ClipboardEvent`, honoring the same `filter_min_size` and
+    /// content-hash dedup the native monitor backends use.
+    pub fn on_format_data_response(&self, format_id: u32, bytes: Vec<u8>) {
+        let Some(text) = format::decode_text(format_id, &bytes) else {
+            tracing::warn!("Ignoring CLIPRDR data for unsupported format id {}", format_id);
+            return;
+        };
+        let content = ClipboardContent::Text(text);
+
+        if content.encoded_len() <= self.filter_min_size {
+            return;
+        }
+
+        let mut last_remote = self.last_remote.lock().expect("last_remote mutex poisoned");
+        let changed = match last_remote.as_ref() {
+            Some(last) => last.content_hash() != content.content_hash(),
+            None => true,
+        };
+        if !changed {
+            return;
+        }
+        *last_remote = Some(content.clone());
+        drop(last_remote);
+
+        let event = ClipboardEvent::new_clipboard(content);
+        if self.sender.send(event).is_err() {
+            tracing::info!("No receiver for remote CLIPRDR clipboard event.");
+        }
+    }
+}
+
+fn available_format_ids(content: &ClipboardContent) -> Vec<u32> {
+    let mut ids = Vec::new();
+    if content.text_fallback().is_some() {
+        if let Some(id) = format::mime_to_format_id("text/plain") {
+            ids.push(id);
+        }
+    }
+    ids
+}
@@ -0,0 +1,151 @@
+use std::collections::{hash_map::DefaultHasher, BTreeMap};
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+/// An X11/Wayland selection target name, e.g. `"text/html"`,
+/// `"text/uri-list"`, `"application/rtf"`, or a vendor-specific type such
+/// as `"application/x-libreoffice-..."`.
+pub type MimeType = String;
+
+/// The payload carried by a [`ClipboardEvent`](crate::ClipboardEvent) or
+/// stored as [`ClipboardData`](clipcat::ClipboardData).
+///
+/// `copypasta` (and most X11/Wayland selection APIs) only expose plain
+/// text, but clipboards routinely carry images (screenshots, copied from
+/// an image viewer or browser) as raw pixel data. `ClipboardContent` lets
+/// the monitor and the history store carry either without collapsing
+/// everything down to `String`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClipboardContent {
+    Text(String),
+
+    /// Raw RGBA8 pixels, following `arboard::ImageData`: `bytes.len()` is
+    /// expected to be `width * height * 4`.
+    Image { width: usize, height: usize, bytes: Vec<u8> },
+
+    /// Every selection target the source offered that also matched the
+    /// watcher's `allowed_mime_types` allow-list, keyed by MIME type. Always
+    /// carries a `"text/plain"` (or `"UTF8_STRING"`) entry when the source
+    /// offered one, so terminal pastes still get a plain-text fallback
+    /// while rich-text targets (e.g. `text/html`) are preserved alongside
+    /// it.
+    Mime(BTreeMap<MimeType, Vec<u8>>),
+}
+
+impl ClipboardContent {
+    /// Length used for `filter_min_size` comparisons: UTF-8 byte length
+    /// for text, PNG-encoded byte length for images (the size the entry
+    /// would actually occupy once persisted to history).
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            ClipboardContent::Text(text) => text.len(),
+            ClipboardContent::Image { .. } => self.encode_png().map(|png| png.len()).unwrap_or(0),
+            ClipboardContent::Mime(representations) => {
+                representations.values().map(Vec::len).sum()
+            }
+        }
+    }
+
+    /// The plain-text fallback for a `Mime` entry, if the source offered
+    /// one under any of the common plain-text target names.
+    pub fn text_fallback(&self) -> Option<&[u8]> {
+        match self {
+            ClipboardContent::Text(text) => Some(text.as_bytes()),
+            ClipboardContent::Image { .. } => None,
+            ClipboardContent::Mime(representations) => ["text/plain", "UTF8_STRING", "STRING"]
+                .iter()
+                .find_map(|mime| representations.get(*mime).map(Vec::as_slice)),
+        }
+    }
+
+    /// Encodes an `Image` variant to PNG bytes for storage; `Text` is
+    /// returned as its UTF-8 bytes.
+    pub fn encode_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        match self {
+            ClipboardContent::Text(text) => Ok(text.as_bytes().to_vec()),
+            ClipboardContent::Image { width, height, bytes } => {
+                let mut png_bytes = Vec::new();
+                let mut encoder = png::Encoder::new(&mut png_bytes, *width as u32, *height as u32);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                let mut writer = encoder.write_header()?;
+                writer.write_image_data(bytes)?;
+                drop(writer);
+                Ok(png_bytes)
+            }
+            ClipboardContent::Mime(_) => {
+                Ok(self.text_fallback().map(<[u8]>::to_vec).unwrap_or_default())
+            }
+        }
+    }
+
+    /// Hash of the content, used by the monitor to detect a genuine
+    /// clipboard change instead of comparing raw bytes (which breaks down
+    /// once a `Text` entry and an `Image` entry can otherwise look equal
+    /// at the byte level).
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        match self {
+            ClipboardContent::Text(text) => {
+                0u8.hash(&mut hasher);
+                text.hash(&mut hasher);
+            }
+            ClipboardContent::Image { width, height, bytes } => {
+                1u8.hash(&mut hasher);
+                width.hash(&mut hasher);
+                height.hash(&mut hasher);
+                bytes.hash(&mut hasher);
+            }
+            ClipboardContent::Mime(representations) => {
+                2u8.hash(&mut hasher);
+                for (mime, bytes) in representations {
+                    mime.hash(&mut hasher);
+                    bytes.hash(&mut hasher);
+                }
+            }
+        }
+        hasher.finish()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        match self {
+            ClipboardContent::Text(text) => text.is_empty(),
+            ClipboardContent::Image { bytes, .. } => bytes.is_empty(),
+            ClipboardContent::Mime(representations) => representations.is_empty(),
+        }
+    }
+}
+
+impl From<String> for ClipboardContent {
+    fn from(text: String) -> Self { ClipboardContent::Text(text) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hash_distinguishes_text_and_image_with_equal_bytes() {
+        let text = ClipboardContent::Text("\u{0}\u{0}\u{0}\u{0}".to_string());
+        let image = ClipboardContent::Image { width: 1, height: 1, bytes: vec![0, 0, 0, 0] };
+
+        // Byte-for-byte these look the same; only the hash's leading
+        // variant tag should tell them apart.
+        assert_ne!(text.content_hash(), image.content_hash());
+        assert_eq!(text.content_hash(), text.content_hash());
+    }
+
+    #[test]
+    fn mime_text_fallback_prefers_the_plain_text_entry_and_encoded_len_sums_all() {
+        let mut representations = BTreeMap::new();
+        representations.insert("text/html".to_string(), b"<b>hi</b>".to_vec());
+        representations.insert("text/plain".to_string(), b"hi".to_vec());
+        let content = ClipboardContent::Mime(representations);
+
+        assert_eq!(content.text_fallback(), Some(b"hi".as_slice()));
+        assert_eq!(content.encode_png().unwrap(), b"hi");
+        assert_eq!(content.encoded_len(), "<b>hi</b>".len() + "hi".len());
+    }
+}
@@ -11,4 +11,8 @@ pub enum ClipboardError {
     #[cfg(feature = "monitor")]
     #[snafu(display("Could not paste to clipboard, error: {}", source))]
     PasteToX11Clipboard { source: x11_clipboard::error::Error },
+
+    #[cfg(feature = "monitor")]
+    #[snafu(display("Could not initialize Wayland clipboard, error: {}", source))]
+    InitializeWaylandClipboard { source: wayland_client::ConnectError },
 }
@@ -11,7 +11,10 @@ use copypasta::{ClipboardContext, ClipboardProvider};
 use snafu::ResultExt;
 use tokio::sync::broadcast::{self, error::SendError};
 
-use crate::{error, ClipboardError, ClipboardEvent, ClipboardType, MonitorState};
+use crate::{
+    content::ClipboardContent, error, provider::ClipboardProviderConfig, ClipboardError,
+    ClipboardEvent, ClipboardType, MonitorState,
+};
 
 pub struct ClipboardMonitor {
     is_running: Arc<AtomicBool>,
@@ -20,12 +23,25 @@ pub struct ClipboardMonitor {
     primary_thread: Option<thread::JoinHandle<()>>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct ClipboardMonitorOptions {
     pub load_current: bool,
     pub enable_clipboard: bool,
     pub enable_primary: bool,
     pub filter_min_size: usize,
+
+    /// Extra MIME types to capture alongside plain text/image, e.g.
+    /// `text/html`, `text/uri-list`, `application/rtf`. When non-empty, a
+    /// matching selection is captured as `ClipboardContent::Mime` (all
+    /// requested representations that the source actually offered) instead
+    /// of being collapsed to `Text`.
+    pub allowed_mime_types: Vec<String>,
+
+    /// External-command provider to use instead of the native X11/Wayland
+    /// context. `None` means: the native Wayland backend when
+    /// `WAYLAND_DISPLAY` is set, the native X11 context when only `DISPLAY`
+    /// is set, and `provider::detect_default()` when neither is set.
+    pub provider: Option<ClipboardProviderConfig>,
 }
 
 impl Default for ClipboardMonitorOptions {
@@ -35,6 +51,8 @@ impl Default for ClipboardMonitorOptions {
             enable_clipboard: true,
             enable_primary: true,
             filter_min_size: 0,
+            allowed_mime_types: Vec::new(),
+            provider: None,
         }
     }
 }
@@ -51,25 +69,75 @@ impl ClipboardMonitor {
             primary_thread: None,
         };
 
+        // The backend is picked per-session rather than purely at compile
+        // time: a build with both the `x11` and `wayland` features still
+        // needs to know, at `new()` time, which display server it actually
+        // landed on. An explicit `opts.provider` always wins, since that's
+        // the user asking for a specific external command rather than
+        // "whatever this session has". Otherwise, if neither `DISPLAY` nor
+        // `WAYLAND_DISPLAY` is set at all (headless/SSH/container), we
+        // can't use either native backend, so fall back to whatever
+        // external-command provider `provider::detect_default` finds.
+        let has_display_server =
+            std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let provider = opts.provider.clone().or_else(|| {
+            if has_display_server {
+                None
+            } else {
+                tracing::info!("No display server detected, auto-detecting a clipboard provider");
+                Some(crate::provider::detect_default())
+            }
+        });
+        let use_wayland = provider.is_none() && crate::wayland::is_wayland_session();
+
         if opts.enable_clipboard {
-            let thread = build_thread(
-                opts.load_current,
-                is_running.clone(),
-                ClipboardType::Clipboard,
-                event_sender.clone(),
-                opts.filter_min_size,
-            )?;
+            let thread = if use_wayland {
+                crate::wayland::build_thread(
+                    opts.load_current,
+                    is_running.clone(),
+                    ClipboardType::Clipboard,
+                    event_sender.clone(),
+                    opts.filter_min_size,
+                    opts.allowed_mime_types.clone(),
+                )?
+            } else {
+                build_thread(
+                    opts.load_current,
+                    is_running.clone(),
+                    ClipboardType::Clipboard,
+                    event_sender.clone(),
+                    opts.filter_min_size,
+                    opts.allowed_mime_types.clone(),
+                    provider.clone(),
+                )?
+            };
             monitor.clipboard_thread = Some(thread);
         }
 
         if opts.enable_primary {
-            let thread = build_thread(
-                opts.load_current,
-                is_running,
-                ClipboardType::Primary,
-                event_sender,
-                opts.filter_min_size,
-            )?;
+            // Under Wayland this is no longer a hard error: the Wayland
+            // backend serves the primary selection from the same
+            // connection as the regular clipboard.
+            let thread = if use_wayland {
+                crate::wayland::build_thread(
+                    opts.load_current,
+                    is_running,
+                    ClipboardType::Primary,
+                    event_sender,
+                    opts.filter_min_size,
+                    opts.allowed_mime_types,
+                )?
+            } else {
+                build_thread(
+                    opts.load_current,
+                    is_running,
+                    ClipboardType::Primary,
+                    event_sender,
+                    opts.filter_min_size,
+                    opts.allowed_mime_types.clone(),
+                    provider,
+                )?
+            };
             monitor.primary_thread = Some(thread);
         }
 
@@ -123,72 +191,118 @@ fn build_thread(
     clipboard_type: ClipboardType,
     sender: broadcast::Sender<ClipboardEvent>,
     filter_min_size: usize,
+    allowed_mime_types: Vec<String>,
+    provider: Option<ClipboardProviderConfig>,
 ) -> Result<thread::JoinHandle<()>, ClipboardError> {
-    let get_clipboard = || match clipboard_type {
-        ClipboardType::Clipboard => ClipboardContext::new(),
-        ClipboardType::Primary => {
-            #[cfg(feature = "wayland")]
-            return Err("Primary clipboard integration not supported on wayland.");
-
-            #[cfg(feature = "x11")]
-            use copypasta::x11_clipboard::{Primary, X11ClipboardContext};
-            #[cfg(feature = "x11")]
-            X11ClipboardContext::<Primary>::new()
+    // Without a `provider`, this backend only runs in an X11 (or XWayland)
+    // session — `ClipboardMonitor::new` routes Wayland sessions to
+    // `wayland::build_thread` unless a provider is set. When `provider` is
+    // set (explicitly configured, or auto-detected because neither
+    // `DISPLAY` nor `WAYLAND_DISPLAY` is set) we shell out instead of
+    // linking X11 at all.
+    let get_clipboard = move || -> Result<Box<dyn copypasta::ClipboardProvider>, _> {
+        if let Some(provider) = &provider {
+            return Ok(Box::new(crate::provider::ExternalCommandProvider::new(
+                provider,
+                clipboard_type,
+            )));
         }
+        match clipboard_type {
+            ClipboardType::Clipboard => {
+                ClipboardContext::new().map(|ctx| Box::new(ctx) as Box<dyn copypasta::ClipboardProvider>)
+            }
+            ClipboardType::Primary => {
+                use copypasta::x11_clipboard::{Primary, X11ClipboardContext};
+                X11ClipboardContext::<Primary>::new()
+                    .map(|ctx| Box::new(ctx) as Box<dyn copypasta::ClipboardProvider>)
+            }
+        }
+    };
+
+    // Image support rides alongside the text-only `copypasta` context: most
+    // platforms expose a separate "image" target on the same selection, so
+    // we keep a second, `arboard`-backed handle purely for pulling raw RGBA
+    // pixels off it.
+    let get_image_clipboard = || match clipboard_type {
+        ClipboardType::Clipboard => arboard::Clipboard::new().ok(),
+        // `arboard` has no primary-selection support; images copied to the
+        // primary selection fall back to the text path below.
+        ClipboardType::Primary => None,
     };
 
-    let send_event = move |data: &str| {
+    let send_event = move |content: ClipboardContent| {
         let event = match clipboard_type {
-            ClipboardType::Clipboard => ClipboardEvent::new_clipboard(data),
-            ClipboardType::Primary => ClipboardEvent::new_primary(data),
+            ClipboardType::Clipboard => ClipboardEvent::new_clipboard(content),
+            ClipboardType::Primary => ClipboardEvent::new_primary(content),
         };
         sender.send(event)
     };
 
     let clipboard: Box<dyn copypasta::ClipboardProvider> =
-        get_clipboard.context(error::InitializeX11Clipboard)?;
+        get_clipboard().context(error::InitializeX11Clipboard)?;
 
     let join_handle = thread::spawn(move || {
         let mut clipboard = clipboard;
+        let mut image_clipboard = get_image_clipboard();
 
         let mut last = if load_current {
-            let result = clipboard.load();
-            match result {
-                Ok(data) => {
-                    if data.len() > filter_min_size {
-                        if let Err(SendError(_curr)) = send_event(&data) {
-                            tracing::info!("ClipboardEvent receiver is closed.");
-                            return;
-                        }
+            match load_content(&mut clipboard, &mut image_clipboard, clipboard_type, &allowed_mime_types) {
+                Some(content) if content.encoded_len() > filter_min_size => {
+                    let content_to_send = content.clone();
+                    if let Err(SendError(_content)) = send_event(content_to_send) {
+                        tracing::info!("ClipboardEvent receiver is closed.");
+                        return;
                     }
-                    data
+                    Some(content)
                 }
-                Err(_) => String::new(),
+                other => other,
             }
         } else {
-            String::new()
+            None
         };
 
         loop {
             let result = clipboard.load_wait();
             match result {
-                Ok(curr) => {
+                Ok(_text) => {
+                    let Some(curr) = load_content(
+                        &mut clipboard,
+                        &mut image_clipboard,
+                        clipboard_type,
+                        &allowed_mime_types,
+                    ) else {
+                        continue;
+                    };
+
+                    let changed = match &last {
+                        Some(last) => last.content_hash() != curr.content_hash(),
+                        None => true,
+                    };
+
                     if is_running.load(Ordering::Acquire)
-                        && curr.len() > filter_min_size
-                        && last.as_bytes() != curr.as_bytes()
+                        && curr.encoded_len() > filter_min_size
+                        && changed
                     {
-                        if let Err(SendError(_curr)) = send_event(&last) {
+                        let content_to_send = curr.clone();
+                        if let Err(SendError(_content)) = send_event(content_to_send) {
                             tracing::info!("ClipboardEvent receiver is closed.");
                             return;
                         };
                     }
+                    last = Some(curr);
                 }
                 Err(err) => {
                     tracing::error!(
                         "Failed to load clipboard, error: {}. Restarting clipboard provider.",
                         err,
                     );
-                    clipboard = match get_clipboard {
+                    // `load_wait` can fail immediately (e.g. a misconfigured
+                    // external-command provider whose executable doesn't
+                    // exist), so back off before retrying instead of
+                    // spinning the thread and re-spawning a failing child
+                    // process as fast as the scheduler allows.
+                    thread::sleep(std::time::Duration::from_millis(250));
+                    clipboard = match get_clipboard() {
                         Ok(c) => c,
                         Err(err) => {
                             tracing::error!("Failed to restart clipboard provider, error: {}", err);
@@ -203,6 +317,117 @@ fn build_thread(
     Ok(join_handle)
 }
 
+/// Reads the current selection, preferring (in order): the requested
+/// custom MIME types, an image, then plain text. Returns `None` if none of
+/// those are available.
+fn load_content(
+    clipboard: &mut Box<dyn copypasta::ClipboardProvider>,
+    image_clipboard: &mut Option<arboard::Clipboard>,
+    clipboard_type: ClipboardType,
+    allowed_mime_types: &[String],
+) -> Option<ClipboardContent> {
+    if !allowed_mime_types.is_empty() {
+        if let Some(content) = load_mime_content(clipboard_type, allowed_mime_types) {
+            return Some(content);
+        }
+    }
+
+    if let Some(image_clipboard) = image_clipboard {
+        if let Ok(image) = image_clipboard.get_image() {
+            return Some(ClipboardContent::Image {
+                width: image.width,
+                height: image.height,
+                bytes: image.bytes.into_owned(),
+            });
+        }
+    }
+
+    clipboard.load().ok().map(ClipboardContent::Text)
+}
+
+/// The plain-text target names always requested alongside
+/// `allowed_mime_types`, so a `Mime` entry always carries a plain-text
+/// fallback (per `ClipboardContent::Mime`'s documented invariant) even
+/// when the caller's allow-list doesn't mention plain text at all.
+const PLAIN_TEXT_TARGETS: [&str; 3] = ["text/plain", "UTF8_STRING", "STRING"];
+
+/// Every target `load_mime_content` requests: the caller's allow-list plus
+/// [`PLAIN_TEXT_TARGETS`], deduplicated, so a `Mime` result always carries
+/// a plain-text fallback per `ClipboardContent::Mime`'s documented
+/// invariant, even when the allow-list doesn't mention plain text at all.
+fn wanted_mime_types(allowed_mime_types: &[String]) -> Vec<&str> {
+    let mut wanted: Vec<&str> = allowed_mime_types.iter().map(String::as_str).collect();
+    for target in PLAIN_TEXT_TARGETS {
+        if !wanted.contains(&target) {
+            wanted.push(target);
+        }
+    }
+    wanted
+}
+
+/// Requests every target in `allowed_mime_types` (plus the plain-text
+/// fallback targets) from the current selection owner one by one, each
+/// with a 100ms timeout, mirroring smithay-clipboard's custom-MIME support.
+/// The owner's X11 `TARGETS` list is not read; a target the owner does not
+/// offer fails or comes back empty and is left out. Returns `None` if the
+/// selection doesn't offer any of the requested types (so the caller
+/// falls back to the image/text path).
+fn load_mime_content(
+    clipboard_type: ClipboardType,
+    allowed_mime_types: &[String],
+) -> Option<ClipboardContent> {
+    let clipboard = x11_clipboard::Clipboard::new().ok()?;
+    let selection = match clipboard_type {
+        ClipboardType::Clipboard => clipboard.setter.atoms.clipboard,
+        ClipboardType::Primary => clipboard.setter.atoms.primary,
+    };
+
+    let mut representations = std::collections::BTreeMap::new();
+    for mime in wanted_mime_types(allowed_mime_types) {
+        let target = clipboard.setter.atoms.property;
+        if let Ok(bytes) = clipboard.load(
+            selection,
+            clipboard.getter.get_atom(mime).unwrap_or(target),
+            target,
+            std::time::Duration::from_millis(100),
+        ) {
+            if !bytes.is_empty() {
+                representations.insert(mime.to_string(), bytes);
+            }
+        }
+    }
+
+    if representations.is_empty() {
+        None
+    } else {
+        Some(ClipboardContent::Mime(representations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wanted_mime_types_always_includes_the_plain_text_fallback() {
+        let allowed = vec!["text/html".to_string()];
+        let wanted = wanted_mime_types(&allowed);
+
+        assert!(wanted.contains(&"text/html"));
+        for target in PLAIN_TEXT_TARGETS {
+            assert!(wanted.contains(&target), "missing fallback target {target}");
+        }
+    }
+
+    #[test]
+    fn wanted_mime_types_does_not_duplicate_an_explicitly_allowed_fallback_target() {
+        let allowed = vec!["text/plain".to_string()];
+        let wanted = wanted_mime_types(&allowed);
+
+        assert_eq!(wanted.iter().filter(|&&mime| mime == "text/plain").count(), 1);
+    }
+}
+
 // type ClipResult<T> = std::result::Result<T, Box<dyn std::error::Error + Send
 // + Sync + 'static>>;
 
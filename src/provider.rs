@@ -0,0 +1,336 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ClipboardType;
+
+/// Standard (padded) base64 alphabet, used to encode OSC 52 payloads.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        out.push(BASE64_ALPHABET[(n >> 18 & 0x3f) as usize] as char);
+        out.push(BASE64_ALPHABET[(n >> 12 & 0x3f) as usize] as char);
+        out.push(if chunk.len() > 1 { BASE64_ALPHABET[(n >> 6 & 0x3f) as usize] as char } else { '=' });
+        out.push(if chunk.len() > 2 { BASE64_ALPHABET[(n & 0x3f) as usize] as char } else { '=' });
+    }
+    out
+}
+
+/// Builds the OSC 52 escape sequence that sets the `c` (clipboard) or `p`
+/// (primary-selection) selection to `data`, terminated with BEL per
+/// xterm's convention.
+fn osc52_sequence(clipboard_type: ClipboardType, data: &[u8]) -> String {
+    let selection = match clipboard_type {
+        ClipboardType::Clipboard => 'c',
+        ClipboardType::Primary => 'p',
+    };
+    format!("\x1b]52;{selection};{}\x07", base64_encode(data))
+}
+
+/// Writes `data` to the clipboard/primary selection via OSC 52, addressed
+/// to `/dev/tty` rather than stdout so it still reaches the controlling
+/// terminal even when clipcat's stdout is redirected.
+fn termcode_copy(clipboard_type: ClipboardType, data: &[u8]) -> std::io::Result<()> {
+    let mut tty = std::fs::OpenOptions::new().write(true).open("/dev/tty")?;
+    tty.write_all(osc52_sequence(clipboard_type, data).as_bytes())
+}
+
+/// A single external command plus its arguments, e.g.
+/// `{ command = "wl-copy", args = [] }`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExternalCommand {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl ExternalCommand {
+    fn new(command: &str, args: &[&str]) -> Self {
+        Self { command: command.to_string(), args: args.iter().map(|arg| arg.to_string()).collect() }
+    }
+
+    /// `Some(self)` if a command is actually configured, `None` for the
+    /// `ExternalCommand::default()` used by providers with no read/write
+    /// side (e.g. `Termcode`'s `paste`), so callers can tell "not
+    /// configured" apart from "configured to run an empty command".
+    fn configured(&self) -> Option<&Self> {
+        (!self.command.is_empty()).then_some(self)
+    }
+
+    fn run(&self, stdin: Option<&[u8]>) -> std::io::Result<Vec<u8>> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        if let Some(bytes) = stdin {
+            child.stdin.take().expect("stdin was piped").write_all(bytes)?;
+        }
+
+        let output = child.wait_with_output()?;
+        Ok(output.stdout)
+    }
+}
+
+/// The four commands a fully-configured provider needs: read/write the
+/// regular clipboard, and optionally read/write the primary selection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomProviderConfig {
+    pub paste: ExternalCommand,
+    pub copy: ExternalCommand,
+    #[serde(default)]
+    pub primary_paste: Option<ExternalCommand>,
+    #[serde(default)]
+    pub primary_copy: Option<ExternalCommand>,
+}
+
+/// Named, ready-made providers plus a `Custom` escape hatch, following
+/// Helix's `clipboard-provider` design: yank/paste is driven by
+/// configurable shell commands instead of a compiled-in X11/Wayland
+/// library, so clipcat can run headless, over SSH, or in a container where
+/// linking those libraries isn't viable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum ClipboardProviderConfig {
+    Wayland,
+    XClip,
+    XSel,
+    Pasteboard,
+    Tmux,
+    /// Writes OSC 52 (`\x1b]52;...\x07`) escape sequences to the
+    /// controlling terminal on copy; has no read side, so `paste` always
+    /// returns an empty string.
+    Termcode,
+    /// `win32yank`, for clipboard access from WSL.
+    Win32Yank,
+    Custom(CustomProviderConfig),
+}
+
+impl ClipboardProviderConfig {
+    fn commands(&self) -> CustomProviderConfig {
+        match self {
+            ClipboardProviderConfig::Wayland => CustomProviderConfig {
+                paste: ExternalCommand::new("wl-paste", &["--no-newline"]),
+                copy: ExternalCommand::new("wl-copy", &[]),
+                primary_paste: Some(ExternalCommand::new("wl-paste", &["--no-newline", "--primary"])),
+                primary_copy: Some(ExternalCommand::new("wl-copy", &["--primary"])),
+            },
+            ClipboardProviderConfig::XClip => CustomProviderConfig {
+                paste: ExternalCommand::new("xclip", &["-selection", "clipboard", "-o"]),
+                copy: ExternalCommand::new("xclip", &["-selection", "clipboard", "-in"]),
+                primary_paste: Some(ExternalCommand::new("xclip", &["-selection", "primary", "-o"])),
+                primary_copy: Some(ExternalCommand::new("xclip", &["-selection", "primary", "-in"])),
+            },
+            ClipboardProviderConfig::XSel => CustomProviderConfig {
+                paste: ExternalCommand::new("xsel", &["-b", "-o"]),
+                copy: ExternalCommand::new("xsel", &["-b", "-i"]),
+                primary_paste: Some(ExternalCommand::new("xsel", &["-p", "-o"])),
+                primary_copy: Some(ExternalCommand::new("xsel", &["-p", "-i"])),
+            },
+            ClipboardProviderConfig::Pasteboard => CustomProviderConfig {
+                paste: ExternalCommand::new("pbpaste", &[]),
+                copy: ExternalCommand::new("pbcopy", &[]),
+                primary_paste: None,
+                primary_copy: None,
+            },
+            ClipboardProviderConfig::Tmux => CustomProviderConfig {
+                paste: ExternalCommand::new("tmux", &["save-buffer", "-"]),
+                copy: ExternalCommand::new("tmux", &["load-buffer", "-"]),
+                primary_paste: None,
+                primary_copy: None,
+            },
+            ClipboardProviderConfig::Termcode => CustomProviderConfig {
+                paste: ExternalCommand::default(),
+                copy: ExternalCommand::default(),
+                primary_paste: None,
+                primary_copy: None,
+            },
+            ClipboardProviderConfig::Win32Yank => CustomProviderConfig {
+                paste: ExternalCommand::new("win32yank.exe", &["-o", "--lf"]),
+                copy: ExternalCommand::new("win32yank.exe", &["-i", "--crlf"]),
+                primary_paste: None,
+                primary_copy: None,
+            },
+            ClipboardProviderConfig::Custom(config) => config.clone(),
+        }
+    }
+}
+
+/// Auto-detects a sensible default from the environment and executable
+/// presence: Wayland/X11 session env vars first, then `$TMUX`, then
+/// whichever of the platform tools is actually on `$PATH`.
+pub fn detect_default() -> ClipboardProviderConfig {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() && executable_exists("wl-copy") {
+        return ClipboardProviderConfig::Wayland;
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        if executable_exists("xclip") {
+            return ClipboardProviderConfig::XClip;
+        }
+        if executable_exists("xsel") {
+            return ClipboardProviderConfig::XSel;
+        }
+    }
+    if std::env::var_os("TMUX").is_some() && executable_exists("tmux") {
+        return ClipboardProviderConfig::Tmux;
+    }
+    if executable_exists("pbcopy") && executable_exists("pbpaste") {
+        return ClipboardProviderConfig::Pasteboard;
+    }
+    if executable_exists("win32yank.exe") {
+        return ClipboardProviderConfig::Win32Yank;
+    }
+    ClipboardProviderConfig::Termcode
+}
+
+fn executable_exists(name: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|path| {
+            std::env::split_paths(&path).any(|dir| dir.join(name).is_file())
+        })
+        .unwrap_or(false)
+}
+
+/// A `copypasta::ClipboardProvider` backed by external commands rather
+/// than a linked X11/Wayland library.
+pub struct ExternalCommandProvider {
+    commands: CustomProviderConfig,
+    clipboard_type: ClipboardType,
+    /// `Termcode`'s copy side isn't an external command at all (it writes
+    /// an OSC 52 escape sequence directly to `/dev/tty`), so `store` needs
+    /// to know to take that path instead of `copy_command`'s (empty,
+    /// unconfigured) command.
+    is_termcode: bool,
+}
+
+impl ExternalCommandProvider {
+    pub fn new(config: &ClipboardProviderConfig, clipboard_type: ClipboardType) -> Self {
+        Self {
+            commands: config.commands(),
+            clipboard_type,
+            is_termcode: matches!(config, ClipboardProviderConfig::Termcode),
+        }
+    }
+
+    fn paste_command(&self) -> Option<&ExternalCommand> {
+        match self.clipboard_type {
+            ClipboardType::Clipboard => self.commands.paste.configured(),
+            ClipboardType::Primary => self.commands.primary_paste.as_ref().and_then(ExternalCommand::configured),
+        }
+    }
+
+    fn copy_command(&self) -> Option<&ExternalCommand> {
+        match self.clipboard_type {
+            ClipboardType::Clipboard => self.commands.copy.configured(),
+            ClipboardType::Primary => self.commands.primary_copy.as_ref().and_then(ExternalCommand::configured),
+        }
+    }
+}
+
+impl copypasta::ClipboardProvider for ExternalCommandProvider {
+    fn load(&mut self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(paste) = self.paste_command() else {
+            return Ok(String::new());
+        };
+        let bytes = paste.run(None)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn load_wait(&mut self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        // External commands have no change-notification primitive, so we
+        // poll at the same cadence `wayland::build_thread` uses.
+        let mut last = self.load()?;
+        loop {
+            std::thread::sleep(std::time::Duration::from_millis(250));
+            let curr = self.load()?;
+            if curr != last {
+                last = curr;
+                return Ok(last);
+            }
+        }
+    }
+
+    // NOTE: `copypasta::ClipboardProvider::store` only ever takes a single
+    // `String`, so pasting a stored `ClipboardContent::Mime` entry back
+    // out necessarily collapses it to its plain-text fallback — the
+    // multi-representation paste-back half of chunk0-3 ("advertise all
+    // stored representations so a paste into a rich-text app keeps
+    // formatting") is NOT implemented here. Doing it properly needs each
+    // named provider to offer several targets at once (e.g. re-running
+    // `wl-copy --type <mime>` per representation loses clipboard
+    // ownership on each call), which this command-per-paste/copy
+    // abstraction isn't built for. Deliberately deferred pending that
+    // redesign.
+    fn store(&mut self, data: String) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.is_termcode {
+            termcode_copy(self.clipboard_type, data.as_bytes())?;
+            return Ok(());
+        }
+
+        let Some(copy) = self.copy_command() else {
+            return Ok(());
+        };
+        copy.run(Some(data.as_bytes()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn termcode_paste_is_a_true_no_op() {
+        let mut provider =
+            ExternalCommandProvider::new(&ClipboardProviderConfig::Termcode, ClipboardType::Clipboard);
+
+        // No read side is configured, so this must short-circuit before
+        // ever spawning `""` (which would fail with ENOENT).
+        assert_eq!(provider.load().unwrap(), "");
+    }
+
+    #[test]
+    fn osc52_sequence_encodes_selection_and_payload() {
+        let sequence = osc52_sequence(ClipboardType::Clipboard, b"hi");
+        assert_eq!(sequence, "\x1b]52;c;aGk=\x07");
+
+        let sequence = osc52_sequence(ClipboardType::Primary, b"hi");
+        assert_eq!(sequence, "\x1b]52;p;aGk=\x07");
+    }
+
+    #[test]
+    fn base64_encode_pads_to_a_multiple_of_four() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn unconfigured_primary_selection_falls_back_to_none() {
+        let provider =
+            ExternalCommandProvider::new(&ClipboardProviderConfig::Pasteboard, ClipboardType::Primary);
+
+        // Pasteboard has no primary-selection commands at all, as opposed
+        // to Termcode's regular-clipboard commands, which are configured
+        // but deliberately empty.
+        assert!(provider.paste_command().is_none());
+        assert!(provider.copy_command().is_none());
+    }
+
+    #[test]
+    fn executable_exists_checks_path() {
+        assert!(executable_exists("ls"));
+        assert!(!executable_exists("not-a-real-clipcat-test-executable"));
+    }
+}
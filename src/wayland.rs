@@ -0,0 +1,190 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use smithay_clipboard::Clipboard as WaylandHandle;
+use snafu::ResultExt;
+use tokio::sync::broadcast::{self, error::SendError};
+use wayland_client::Connection;
+
+use crate::{content::ClipboardContent, error, ClipboardError, ClipboardEvent, ClipboardType};
+
+/// Native Wayland clipboard + primary-selection backend, built directly on
+/// `wl_data_device`/`wp_primary_selection` (via `smithay-clipboard`) rather
+/// than going through copypasta's X11-only context. Unlike the X11 path,
+/// both `ClipboardType::Clipboard` and `ClipboardType::Primary` are backed
+/// by the same Wayland connection here, so primary-selection support is not
+/// a hard gap under Wayland anymore.
+pub struct WaylandClipboard {
+    handle: WaylandHandle,
+    // `smithay-clipboard` only borrows the raw display pointer below; it
+    // doesn't take ownership of the connection, so we have to keep it
+    // alive for as long as `handle` is.
+    _connection: Connection,
+}
+
+impl WaylandClipboard {
+    /// Connects to the compositor named by `$WAYLAND_DISPLAY` and hands
+    /// the raw display pointer to `smithay-clipboard`, which anchors its
+    /// own hidden `WlSurface` to it and runs the read/watch loop on a
+    /// dedicated worker thread internally.
+    pub fn new() -> Result<Self, ClipboardError> {
+        let connection = Connection::connect_to_env().context(error::InitializeWaylandClipboard)?;
+        let display_ptr = connection.backend().display_ptr();
+        // Safety: `display_ptr` comes from the `Connection` we keep alive
+        // in `_connection`, so it outlives `handle`.
+        let handle = unsafe { WaylandHandle::new(display_ptr.cast()) };
+        Ok(Self { handle, _connection: connection })
+    }
+
+    fn load(&self, clipboard_type: ClipboardType) -> Result<String, String> {
+        match clipboard_type {
+            ClipboardType::Clipboard => self.handle.load().map_err(|err| err.to_string()),
+            ClipboardType::Primary => self.handle.load_primary().map_err(|err| err.to_string()),
+        }
+    }
+
+    // Takes a single `String`, same as `smithay_clipboard::Clipboard`'s own
+    // `store`/`store_primary`: re-serving a stored `ClipboardContent::Mime`
+    // entry with all of its representations (so a paste into a rich-text
+    // app keeps formatting) isn't implemented — deliberately deferred, see
+    // `ExternalCommandProvider::store`'s note for why.
+    fn store(&self, clipboard_type: ClipboardType, text: String) {
+        match clipboard_type {
+            ClipboardType::Clipboard => self.handle.store(text),
+            ClipboardType::Primary => self.handle.store_primary(text),
+        }
+    }
+
+    /// Fetches every requested MIME type the current selection offers
+    /// (plus a plain-text fallback, always requested so a `Mime` entry
+    /// always carries one per `ClipboardContent::Mime`'s documented
+    /// invariant), using `smithay-clipboard`'s custom-mime support rather
+    /// than the plain-text-only `load`/`load_primary`.
+    fn load_mime_representations(
+        &self,
+        clipboard_type: ClipboardType,
+        allowed_mime_types: &[String],
+    ) -> std::collections::BTreeMap<String, Vec<u8>> {
+        let mut representations = std::collections::BTreeMap::new();
+        let wanted_mime_types =
+            allowed_mime_types.iter().map(String::as_str).chain(PLAIN_TEXT_TARGETS);
+        for mime in wanted_mime_types {
+            let loaded = match clipboard_type {
+                ClipboardType::Clipboard => self.handle.load_mime(mime),
+                ClipboardType::Primary => self.handle.load_primary_mime(mime),
+            };
+            if let Ok(bytes) = loaded {
+                if !bytes.is_empty() {
+                    representations.insert(mime.to_string(), bytes);
+                }
+            }
+        }
+        representations
+    }
+}
+
+/// The plain-text target names always requested alongside
+/// `allowed_mime_types`, matching `monitor::PLAIN_TEXT_TARGETS`.
+const PLAIN_TEXT_TARGETS: [&str; 3] = ["text/plain", "UTF8_STRING", "STRING"];
+
+/// Detects whether the current session should use the Wayland backend:
+/// `WAYLAND_DISPLAY` takes priority over `DISPLAY` so an XWayland-exported
+/// `DISPLAY` (common under wlroots/COSMIC/GNOME-Wayland) doesn't silently
+/// steer us back to the X11 path.
+pub fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Runs the Wayland read/watch loop on a dedicated worker thread, mirroring
+/// `monitor::build_thread`'s shape: poll for a change, dedup by content
+/// hash, forward a `ClipboardEvent` to every subscriber.
+pub fn build_thread(
+    load_current: bool,
+    is_running: Arc<AtomicBool>,
+    clipboard_type: ClipboardType,
+    sender: broadcast::Sender<ClipboardEvent>,
+    filter_min_size: usize,
+    allowed_mime_types: Vec<String>,
+) -> Result<thread::JoinHandle<()>, ClipboardError> {
+    let clipboard = WaylandClipboard::new()?;
+
+    let send_event = move |content: ClipboardContent| {
+        let event = match clipboard_type {
+            ClipboardType::Clipboard => ClipboardEvent::new_clipboard(content),
+            ClipboardType::Primary => ClipboardEvent::new_primary(content),
+        };
+        sender.send(event)
+    };
+
+    let load_content = |clipboard: &WaylandClipboard| -> Option<ClipboardContent> {
+        if !allowed_mime_types.is_empty() {
+            let representations = clipboard.load_mime_representations(clipboard_type, &allowed_mime_types);
+            if !representations.is_empty() {
+                return Some(ClipboardContent::Mime(representations));
+            }
+        }
+        clipboard.load(clipboard_type).ok().map(ClipboardContent::Text)
+    };
+
+    let join_handle = thread::spawn(move || {
+        let mut last: Option<ClipboardContent> = None;
+
+        if load_current {
+            if let Some(content) = load_content(&clipboard) {
+                if content.encoded_len() > filter_min_size {
+                    if let Err(SendError(_content)) = send_event(content.clone()) {
+                        tracing::info!("ClipboardEvent receiver is closed.");
+                        return;
+                    }
+                }
+                last = Some(content);
+            }
+        }
+
+        // `smithay-clipboard` has no blocking "wait for change" primitive
+        // the way X11 selection-owner events do, so we poll at a modest
+        // interval instead.
+        loop {
+            if !is_running.load(Ordering::Acquire) {
+                thread::sleep(std::time::Duration::from_millis(250));
+                continue;
+            }
+
+            match load_content(&clipboard) {
+                Some(curr) => {
+                    let changed = match &last {
+                        Some(last) => last.content_hash() != curr.content_hash(),
+                        None => true,
+                    };
+
+                    if changed && curr.encoded_len() > filter_min_size {
+                        if let Err(SendError(_content)) = send_event(curr.clone()) {
+                            tracing::info!("ClipboardEvent receiver is closed.");
+                            return;
+                        }
+                    }
+                    last = Some(curr);
+                }
+                None => {
+                    tracing::debug!("Wayland selection unreadable, skipping.");
+                }
+            }
+
+            thread::sleep(std::time::Duration::from_millis(250));
+        }
+    });
+
+    Ok(join_handle)
+}
+
+// `paste` has no caller; it is the only user of `WaylandClipboard::store`,
+// so it keeps that method from raising an unused-method warning.
+#[allow(dead_code)]
+fn paste(clipboard: &WaylandClipboard, clipboard_type: ClipboardType, text: String) {
+    clipboard.store(clipboard_type, text);
+}